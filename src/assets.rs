@@ -0,0 +1,76 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use syntect::dumps::{dump_to_file, from_reader};
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+
+/// The syntax and theme definitions `bat` highlights with.
+///
+/// Loading these from scratch (walking the bundled syntax/theme folders and
+/// parsing every `.sublime-syntax`/`.tmTheme` file) is slow, so we prefer a
+/// pre-dumped binary cache and only fall back to `syntect`'s own embedded
+/// defaults when no cache is available.
+pub struct HighlightingAssets {
+    pub syntax_set: SyntaxSet,
+    pub theme_set: ThemeSet,
+}
+
+fn syntaxes_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("syntaxes.bin")
+}
+
+fn themes_path(cache_dir: &Path) -> PathBuf {
+    cache_dir.join("themes.bin")
+}
+
+impl HighlightingAssets {
+    /// Try the on-disk cache first, then fall back to `syntect`'s embedded
+    /// defaults.
+    pub fn new(cache_dir: &Path) -> Self {
+        Self::from_cache(cache_dir).unwrap_or_else(|_| HighlightingAssets {
+            syntax_set: SyntaxSet::load_defaults_nonewlines(),
+            theme_set: ThemeSet::load_defaults(),
+        })
+    }
+
+    /// Load pre-dumped `syntaxes.bin`/`themes.bin` from `cache_dir`.
+    pub fn from_cache(cache_dir: &Path) -> Result<Self, syntect::LoadingError> {
+        let syntax_set = from_reader(fs::File::open(syntaxes_path(cache_dir))?)?;
+        let theme_set = from_reader(fs::File::open(themes_path(cache_dir))?)?;
+
+        Ok(HighlightingAssets {
+            syntax_set,
+            theme_set,
+        })
+    }
+
+    /// Build fresh assets from the user's `~/.config/bat/{syntaxes,themes}`
+    /// folders. The user's syntaxes are layered on top of the bundled
+    /// default syntaxes; the theme set holds only the themes found in the
+    /// user's `themes` folder, without the bundled default themes.
+    pub fn from_files(config_dir: &Path) -> Result<Self, syntect::LoadingError> {
+        let mut builder = SyntaxSet::load_defaults_nonewlines().into_builder();
+        builder.add_plain_text_syntax();
+        builder.add_from_folder(config_dir.join("syntaxes"), true)?;
+
+        let theme_set = ThemeSet::load_from_folder(config_dir.join("themes"))?;
+
+        Ok(HighlightingAssets {
+            syntax_set: builder.build(),
+            theme_set,
+        })
+    }
+
+    /// Dump `syntax_set`/`theme_set` to `syntaxes.bin`/`themes.bin` inside
+    /// `cache_dir`, creating the directory if necessary.
+    pub fn save_to_cache(&self, cache_dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(cache_dir)?;
+        dump_to_file(&self.syntax_set, syntaxes_path(cache_dir))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        dump_to_file(&self.theme_set, themes_path(cache_dir))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use ansi_term::{
+    Colour::{Fixed, Green, Red, Yellow},
+    Style,
+};
+
+use crate::{LineChange, GRID_COLOR};
+
+/// A single column of the left-hand gutter (line numbers, the git-change
+/// marker, the grid separator, ...). Composing a line out of `Decoration`s
+/// lets `--style` enable/disable individual columns without duplicating the
+/// rendering logic, and lets the panel width follow whatever is actually
+/// being shown.
+pub trait Decoration {
+    fn width(&self) -> usize;
+    fn render(&self, line_nr: usize, line_change: Option<LineChange>) -> String;
+}
+
+pub struct LineNumberDecoration {
+    pub width: usize,
+}
+
+impl Decoration for LineNumberDecoration {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn render(&self, line_nr: usize, _line_change: Option<LineChange>) -> String {
+        Fixed(244)
+            .paint(format!("{:width$}", line_nr, width = self.width))
+            .to_string()
+    }
+}
+
+pub struct LineChangeDecoration;
+
+impl Decoration for LineChangeDecoration {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn render(&self, _line_nr: usize, line_change: Option<LineChange>) -> String {
+        match line_change {
+            Some(LineChange::Added) => Green.paint("+").to_string(),
+            Some(LineChange::RemovedAbove) => Red.paint("‾").to_string(),
+            Some(LineChange::RemovedBelow) => Red.paint("_").to_string(),
+            Some(LineChange::Modified) => Yellow.paint("~").to_string(),
+            None => Style::default().paint(" ").to_string(),
+        }
+    }
+}
+
+pub struct GridDecoration;
+
+impl Decoration for GridDecoration {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn render(&self, _line_nr: usize, _line_change: Option<LineChange>) -> String {
+        Fixed(GRID_COLOR).paint("|").to_string()
+    }
+}
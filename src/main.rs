@@ -1,28 +1,35 @@
+mod assets;
+mod decorations;
+mod output;
+mod terminal;
+
 use std::{
     collections::HashMap,
-    env,
-    io::{self, BufRead, StdoutLock, Write},
-    path::Path,
+    env, fs,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
     process,
 };
 
 #[macro_use]
 extern crate clap;
-use ansi_term::{
-    Colour::{Fixed, Green, Red, White, Yellow},
-    Style,
-};
+use ansi_term::Colour::{Fixed, Red, White};
 use atty::Stream;
 use clap::{Arg, ArgAction, ArgMatches, ColorChoice, Command};
 use console::Term;
 use git2::{DiffOptions, IntoCString, Repository};
-use syntect::util::as_24_bit_terminal_escaped;
 use syntect::{
-    easy::HighlightFile,
-    highlighting::{Theme, ThemeSet},
-    parsing::SyntaxSet,
+    easy::HighlightLines,
+    highlighting::Theme,
+    parsing::{SyntaxReference, SyntaxSet},
 };
 
+use assets::HighlightingAssets;
+use decorations::{Decoration, GridDecoration, LineChangeDecoration, LineNumberDecoration};
+use output::OutputType;
+use terminal::as_terminal_escaped;
+
 #[derive(Copy, Clone, Debug)]
 enum LineChange {
     Added,
@@ -33,31 +40,119 @@ enum LineChange {
 
 type LineChanges = HashMap<u32, LineChange>;
 
-const PANEL_WIDTH: usize = 7;
 const GRID_COLOR: u8 = 238;
 
+/// Theme used when the user hasn't configured one of their own. Must be one
+/// of the themes bundled in `syntect`'s embedded defaults, since that's
+/// what `HighlightingAssets::new` falls back to when there's no cache yet.
+const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+/// How much of the panel (header, grid, gutter) `print_file` draws around
+/// the highlighted text.
+#[derive(Copy, Clone, Debug)]
+pub enum OutputStyle {
+    /// Highlighted text only, no panel at all.
+    Plain,
+    /// The line-number gutter, but no header or horizontal rules.
+    LineNumbers,
+    /// Header, gutter and horizontal rules (today's default look).
+    Full,
+}
+
+impl OutputStyle {
+    fn show_header(self) -> bool {
+        matches!(self, OutputStyle::Full)
+    }
+
+    fn decorations(self) -> Vec<Box<dyn Decoration>> {
+        match self {
+            OutputStyle::Plain => vec![],
+            OutputStyle::LineNumbers | OutputStyle::Full => vec![
+                Box::new(LineNumberDecoration { width: 4 }),
+                Box::new(LineChangeDecoration),
+                Box::new(GridDecoration),
+            ],
+        }
+    }
+}
+
+/// Column at which the trailing `GridDecoration`'s `|` lands, so
+/// `print_horizontal_line`'s `┬┼┴` junctions line up with it. This is the
+/// width of every decoration *before* the grid, each followed by one
+/// space, and deliberately excludes the grid decoration itself.
+fn panel_width(decorations: &[Box<dyn Decoration>]) -> usize {
+    if decorations.is_empty() {
+        0
+    } else {
+        decorations[..decorations.len() - 1]
+            .iter()
+            .map(|d| d.width() + 1)
+            .sum()
+    }
+}
+
 fn print_horizontal_line(
-    handle: &mut StdoutLock,
+    handle: &mut dyn Write,
     grid_char: char,
     term_width: usize,
+    panel_width: usize,
 ) -> io::Result<()> {
-    let bar = "-".repeat(term_width - (PANEL_WIDTH + 1));
-    let line = format!("{}{}{}", "-".repeat(PANEL_WIDTH), grid_char, bar);
+    let bar = "-".repeat(term_width - (panel_width + 1));
+    let line = format!("{}{}{}", "-".repeat(panel_width), grid_char, bar);
 
     write!(handle, "{}\n", Fixed(GRID_COLOR).paint(line))?;
     Ok(())
 }
 
-fn print_file<P: AsRef<Path>>(
+/// Pick which syntax to highlight with: an explicitly forced name/extension
+/// wins, then a match on `display_name`'s extension, then first-line
+/// detection (shebangs, `-*- mode: ... -*-` headers, ...), and finally
+/// plain text.
+fn resolve_syntax<'a>(
+    syntax_set: &'a SyntaxSet,
+    display_name: &str,
+    language: Option<&str>,
+    first_line: &str,
+) -> &'a SyntaxReference {
+    if let Some(lang) = language {
+        if let Some(syntax) = syntax_set.find_syntax_by_token(lang) {
+            return syntax;
+        }
+    }
+
+    let by_extension = Path::new(display_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| syntax_set.find_syntax_by_extension(ext));
+
+    by_extension
+        .or_else(|| syntax_set.find_syntax_by_first_line(first_line))
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text())
+}
+
+fn print_file<R: BufRead>(
     theme: &Theme,
     syntax_set: &SyntaxSet,
-    filename: P,
+    display_name: &str,
+    reader: R,
+    language: Option<&str>,
     line_changes: Option<LineChanges>,
+    output: &mut OutputType,
+    style: OutputStyle,
+    true_color: bool,
 ) -> io::Result<()> {
-    let mut hightlighter = HighlightFile::new(filename.as_ref(), syntax_set, theme)?;
+    let mut lines = reader.lines();
+    let first_line = lines.next();
+    let first_line_text = first_line
+        .as_ref()
+        .and_then(|line| line.as_ref().ok())
+        .cloned()
+        .unwrap_or_default();
 
-    let stdout = io::stdout();
-    let mut handle = stdout.lock();
+    let syntax = resolve_syntax(syntax_set, display_name, language, &first_line_text);
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let handle = output.handle();
 
     let term = Term::stdout();
 
@@ -65,73 +160,191 @@ fn print_file<P: AsRef<Path>>(
 
     let term_width = term_width as usize;
 
-    print_horizontal_line(&mut handle, '┬', term_width)?;
-
-    write!(
-        handle,
-        "{}{} {}\n",
-        " ".repeat(PANEL_WIDTH),
-        Fixed(GRID_COLOR).paint("|"),
-        White.bold().paint(filename.as_ref().to_string_lossy())
-    )?;
+    let decorations = style.decorations();
+    let panel_width = panel_width(&decorations);
 
-    print_horizontal_line(&mut handle, '┼', term_width)?;
+    if style.show_header() {
+        print_horizontal_line(handle, '┬', term_width, panel_width)?;
 
-    for (idx, maybe_line) in hightlighter.reader.lines().enumerate() {
-        let line_nr = idx + 1;
-        let line = maybe_line.unwrap_or("<INVALID UTF-8>".into());
-        let regions = hightlighter
-            .highlight_lines
-            .highlight_line(&line, syntax_set)
-            .ok()
-            .unwrap();
-
-        let line_change = if let Some(ref changes) = line_changes {
-            match changes.get(&(line_nr as u32)) {
-                Some(&LineChange::Added) => Green.paint("+"),
-                Some(&LineChange::RemovedAbove) => Red.paint("‾"),
-                Some(&LineChange::RemovedBelow) => Red.paint("_"),
-                Some(&LineChange::Modified) => Yellow.paint("~"),
-                _ => Style::default().paint(" "),
-            }
-        } else {
-            Style::default().paint(" ")
-        };
         write!(
             handle,
-            "{} {} {} {}\n",
-            Fixed(244).paint(format!("{:4}", line_nr)),
-            line_change,
+            "{}{} {}\n",
+            " ".repeat(panel_width),
             Fixed(GRID_COLOR).paint("|"),
-            as_24_bit_terminal_escaped(&regions, false)
+            White.bold().paint(display_name)
         )?;
+
+        print_horizontal_line(handle, '┼', term_width, panel_width)?;
+    }
+
+    for (idx, maybe_line) in first_line.into_iter().chain(lines).enumerate() {
+        let line_nr = idx + 1;
+        let line = maybe_line.unwrap_or("<INVALID UTF-8>".into());
+        let regions = highlighter
+            .highlight_line(&line, syntax_set)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        let line_change = line_changes
+            .as_ref()
+            .and_then(|changes| changes.get(&(line_nr as u32)))
+            .copied();
+
+        for decoration in &decorations {
+            write!(handle, "{} ", decoration.render(line_nr, line_change))?;
+        }
+
+        write!(handle, "{}\n", as_terminal_escaped(&regions, true_color))?;
+    }
+
+    if style.show_header() {
+        print_horizontal_line(handle, '┴', term_width, panel_width)?;
     }
-    print_horizontal_line(&mut handle, '┴', term_width)?;
 
     Ok(())
 }
 
-fn run(matches: &ArgMatches) -> io::Result<()> {
+fn config_dir() -> io::Result<PathBuf> {
     let home_dir = env::home_dir().ok_or(io::Error::new(
         io::ErrorKind::Other,
         "Could not get home directory",
     ))?;
+    Ok(home_dir.join(".config").join("bat"))
+}
 
-    let theme_dir = home_dir.join(".config").join("bat").join("themes");
+/// Does the terminal advertise 24-bit color support?
+fn colorterm_is_true_color() -> bool {
+    matches!(
+        env::var("COLORTERM").as_deref(),
+        Ok("truecolor") | Ok("24bit")
+    )
+}
 
-    let theme_set = ThemeSet::load_from_folder(theme_dir)
-        .map_err(|_| io::Error::new(io::ErrorKind::Other, "Could not load themes"))?;
-    let theme = &theme_set.themes["Monokai"];
+fn true_color_enabled(matches: &ArgMatches) -> bool {
+    match matches.get_one::<String>("true-color").map(String::as_str) {
+        Some("always") => true,
+        Some("never") => false,
+        _ => colorterm_is_true_color(),
+    }
+}
+
+/// Open `path` for highlighting, rejecting inputs `print_file` can't make
+/// sense of: directories, and (on Unix) sockets/block devices. FIFOs are
+/// let through since reading them as a plain stream works the same as
+/// reading stdin.
+fn open_input(path_str: &str) -> io::Result<BufReader<File>> {
+    let path = Path::new(path_str);
+    let metadata = fs::metadata(path)?;
+
+    if metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "is a directory",
+        ));
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        let file_type = metadata.file_type();
+        if file_type.is_socket() || file_type.is_block_device() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a regular file or stream",
+            ));
+        }
+    }
+
+    Ok(BufReader::new(File::open(path)?))
+}
+
+#[allow(clippy::too_many_arguments)]
+fn print_path(
+    theme: &Theme,
+    syntax_set: &SyntaxSet,
+    file: &str,
+    language: Option<&str>,
+    style: OutputStyle,
+    true_color: bool,
+    output: &mut OutputType,
+) -> io::Result<()> {
+    if file == "-" {
+        let reader = BufReader::new(io::stdin());
+        return print_file(
+            theme, syntax_set, "STDIN", reader, language, None, output, style, true_color,
+        );
+    }
+
+    let reader =
+        open_input(file).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", file, e)))?;
+    let line_changes = get_changes(file.to_string());
+    print_file(
+        theme,
+        syntax_set,
+        file,
+        reader,
+        language,
+        line_changes,
+        output,
+        style,
+        true_color,
+    )
+}
+
+fn run(matches: &ArgMatches) -> io::Result<()> {
+    let config_dir = config_dir()?;
+    let assets = HighlightingAssets::new(&config_dir.join("cache"));
+    let theme = assets.theme_set.themes.get(DEFAULT_THEME).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            format!("Could not load theme '{}'", DEFAULT_THEME),
+        )
+    })?;
+    let syntax_set = &assets.syntax_set;
+
+    let use_paging = matches.get_flag("paging") && atty::is(Stream::Stdout);
+    let mut output = OutputType::from_mode(use_paging);
+
+    let style = if matches.get_flag("plain") {
+        OutputStyle::Plain
+    } else {
+        match matches.get_one::<String>("style").map(String::as_str) {
+            Some("plain") => OutputStyle::Plain,
+            Some("numbers") => OutputStyle::LineNumbers,
+            _ => OutputStyle::Full,
+        }
+    };
 
-    let syntax_set = SyntaxSet::load_defaults_nonewlines();
+    let language = matches.get_one::<String>("language").map(String::as_str);
+    let true_color = true_color_enabled(matches);
+
+    let mut any_succeeded = false;
+    let mut any_failed = false;
 
     if let Some(files) = matches.get_many::<String>("FILE") {
         for file in files {
-            println!("{}", file);
-            let line_changes = get_changes(file.clone());
-            print_file(theme, &syntax_set, file, line_changes)?;
+            match print_path(
+                theme,
+                syntax_set,
+                file,
+                language,
+                style,
+                true_color,
+                &mut output,
+            ) {
+                Ok(()) => any_succeeded = true,
+                Err(e) => {
+                    any_failed = true;
+                    eprintln!("{}: {}", Red.paint("[bat error]"), e);
+                }
+            }
         }
     }
+
+    if any_failed && !any_succeeded {
+        // Individual failures were already reported above; this just
+        // signals a nonzero exit code without printing a second message.
+        return Err(io::Error::new(io::ErrorKind::Other, ""));
+    }
     Ok(())
 }
 
@@ -182,7 +395,6 @@ fn get_changes(filename: String) -> Option<LineChanges> {
         }),
         None,
     );
-    println!("{:?}", line_changes);
 
     Some(line_changes)
 }
@@ -205,14 +417,71 @@ fn main() {
                 .num_args(1..)
                 .help("File(s) to print"),
         )
+        .arg(
+            Arg::new("paging")
+                .short('P')
+                .long("paging")
+                .action(ArgAction::SetTrue)
+                .help("Pipe output through `less` when stdout is a terminal"),
+        )
+        .arg(
+            Arg::new("style")
+                .long("style")
+                .value_parser(["plain", "numbers", "full"])
+                .default_value("full")
+                .help("Output style: 'plain', 'numbers', or 'full'"),
+        )
+        .arg(
+            Arg::new("plain")
+                .short('p')
+                .long("plain")
+                .action(ArgAction::SetTrue)
+                .overrides_with("style")
+                .help("Shortcut for --style=plain"),
+        )
+        .arg(
+            Arg::new("language")
+                .short('l')
+                .long("language")
+                .help("Force a specific syntax, by name or extension (e.g. 'rust', 'json')"),
+        )
+        .arg(
+            Arg::new("true-color")
+                .long("true-color")
+                .value_parser(["auto", "always", "never"])
+                .default_value("auto")
+                .help("Use 24-bit colors instead of the 256-color palette"),
+        )
+        .subcommand(
+            Command::new("cache")
+                .hide(true)
+                .about("Build the syntax/theme cache from the user's config folder"),
+        )
         .get_matches();
 
-    let result = run(&matches);
+    let result = if matches.subcommand_matches("cache").is_some() {
+        build_cache()
+    } else {
+        run(&matches)
+    };
 
     if let Err(e) = result {
         if e.kind() != io::ErrorKind::BrokenPipe {
-            eprintln!("{}: {}", ansi_term::Colour::Red.paint("[bat error]"), e);
+            let message = e.to_string();
+            if !message.is_empty() {
+                eprintln!("{}: {}", Red.paint("[bat error]"), message);
+            }
             process::exit(1);
         }
     }
 }
+
+/// Regenerate `syntaxes.bin`/`themes.bin` from the user's
+/// `~/.config/bat/{syntaxes,themes}` folders.
+fn build_cache() -> io::Result<()> {
+    let config_dir = config_dir()?;
+    let assets = HighlightingAssets::from_files(&config_dir)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    assets.save_to_cache(&config_dir.join("cache"))?;
+    Ok(())
+}
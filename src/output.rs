@@ -0,0 +1,58 @@
+use std::io::{self, Write};
+use std::process::{Child, Command, Stdio};
+
+/// Where highlighted output is written to.
+///
+/// When stdout is a terminal we prefer piping through `less` so that long
+/// files can be scrolled the same way `git diff`/`git log` do. When stdout
+/// is redirected (or paging is disabled) we fall back to writing directly.
+pub enum OutputType {
+    Pager(Child),
+    Stdout(io::Stdout),
+}
+
+impl OutputType {
+    /// Spawn `less` and pipe output to its stdin.
+    pub fn pager() -> io::Result<Self> {
+        let child = Command::new("less")
+            .args(["--quit-if-one-screen", "--RAW-CONTROL-CHARS", "--no-init"])
+            .stdin(Stdio::piped())
+            .spawn()?;
+
+        Ok(OutputType::Pager(child))
+    }
+
+    pub fn stdout() -> Self {
+        OutputType::Stdout(io::stdout())
+    }
+
+    /// Pick a pager when `use_paging` is set, and stdout when it is not or
+    /// when `less` cannot be spawned. Whether stdout is a terminal is not
+    /// checked here; the caller folds that into `use_paging`.
+    pub fn from_mode(use_paging: bool) -> Self {
+        if use_paging {
+            if let Ok(pager) = Self::pager() {
+                return pager;
+            }
+        }
+        Self::stdout()
+    }
+
+    pub fn handle(&mut self) -> &mut dyn Write {
+        match self {
+            OutputType::Pager(child) => child.stdin.as_mut().expect("missing pager stdin"),
+            OutputType::Stdout(stdout) => stdout,
+        }
+    }
+}
+
+impl Drop for OutputType {
+    fn drop(&mut self) {
+        if let OutputType::Pager(child) = self {
+            // Drop our handle to the pager's stdin first so it sees EOF,
+            // then wait for it to exit so the terminal is left in a sane
+            // state before we return control to the shell.
+            let _ = child.wait();
+        }
+    }
+}
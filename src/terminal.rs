@@ -0,0 +1,56 @@
+use syntect::highlighting::{Color, Style};
+
+/// The 6 levels used for each component of the xterm 256-color cube
+/// (indices 16..=231).
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Map an RGB color to the closest xterm-256 palette index, searching the
+/// 6x6x6 color cube plus the 24-step grayscale ramp.
+fn nearest_256_color(color: Color) -> u8 {
+    let (r, g, b) = (color.r as i32, color.g as i32, color.b as i32);
+
+    let distance =
+        |cr: i32, cg: i32, cb: i32| -> i32 { (r - cr).pow(2) + (g - cg).pow(2) + (b - cb).pow(2) };
+
+    let mut best_index = 16u8;
+    let mut best_distance = i32::MAX;
+
+    for (ri, &cr) in CUBE_LEVELS.iter().enumerate() {
+        for (gi, &cg) in CUBE_LEVELS.iter().enumerate() {
+            for (bi, &cb) in CUBE_LEVELS.iter().enumerate() {
+                let d = distance(cr as i32, cg as i32, cb as i32);
+                if d < best_distance {
+                    best_distance = d;
+                    best_index = 16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8;
+                }
+            }
+        }
+    }
+
+    for step in 0..24 {
+        let level = 8 + 10 * step;
+        let d = distance(level, level, level);
+        if d < best_distance {
+            best_distance = d;
+            best_index = 232 + step as u8;
+        }
+    }
+
+    best_index
+}
+
+/// Render highlighted regions as ANSI-escaped text, choosing between
+/// 24-bit truecolor and a nearest-match 256-color fallback depending on
+/// what the terminal supports.
+pub fn as_terminal_escaped(regions: &[(Style, &str)], true_color: bool) -> String {
+    if true_color {
+        return syntect::util::as_24_bit_terminal_escaped(regions, false);
+    }
+
+    let mut escaped = String::new();
+    for (style, text) in regions {
+        let index = nearest_256_color(style.foreground);
+        escaped.push_str(&format!("\x1b[38;5;{}m{}\x1b[0m", index, text));
+    }
+    escaped
+}